@@ -0,0 +1,269 @@
+/*!
+Optional online IP-to-ASN lookup backend that talks to the [Team Cymru] IP-to-ASN DNS service
+instead of a locally loaded [`Db`](crate::Db). Useful for callers who don't want to ship or
+periodically refresh a multi-megabyte TSV file.
+
+To map an IPv4 address `a.b.c.d`, a `TXT` query is issued for `d.c.b.a.origin.asn.cymru.com`;
+the answer is a pipe-delimited string like `"13335 | 1.1.1.0/24 | US | arin | 2010-07-14"`. IPv6
+addresses are looked up the same way under `origin6.asn.cymru.com`, with the address nibble-reversed
+instead of the dotted-octet reversal used for IPv4. The owner name is resolved with a second `TXT`
+query for `AS<number>.asn.cymru.com`, whose answer is `"AS_number | country | registry | allocated | name"`.
+
+This module produces the same [`Record`](crate::Record) type as [`Db`](crate::Db), so callers can
+switch between a local database and live DNS lookups. It requires the `dns` feature.
+
+[Team Cymru]: https://team-cymru.com/community-services/ip-asn-mapping/
+*/
+use crate::{IpAddr, IpNet, IpVersion, Ipv4Addr, Ipv6Addr, Record};
+use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+use hickory_resolver::TokioAsyncResolver;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum CymruError {
+    Resolve(ResolveError),
+    MalformedResponse(&'static str),
+    AsNumberParseError(std::num::ParseIntError),
+    NetParseError(ipnet::AddrParseError),
+}
+
+impl fmt::Display for CymruError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CymruError::Resolve(_) => write!(f, "error resolving Team Cymru DNS record"),
+            CymruError::MalformedResponse(message) => {
+                write!(f, "malformed Team Cymru TXT response: {}", message)
+            }
+            CymruError::AsNumberParseError(_) => {
+                write!(f, "error parsing AS number in Team Cymru TXT response")
+            }
+            CymruError::NetParseError(_) => {
+                write!(f, "error parsing BGP prefix in Team Cymru TXT response")
+            }
+        }
+    }
+}
+
+impl Error for CymruError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CymruError::Resolve(err) => Some(err),
+            CymruError::MalformedResponse(_) => None,
+            CymruError::AsNumberParseError(err) => Some(err),
+            CymruError::NetParseError(err) => Some(err),
+        }
+    }
+}
+
+/// Reverses `a.b.c.d` into the `d.c.b.a.origin.asn.cymru.com.` query name.
+fn origin_v4_name(ip: Ipv4Addr) -> String {
+    let [a, b, c, d] = ip.octets();
+    format!("{}.{}.{}.{}.origin.asn.cymru.com.", d, c, b, a)
+}
+
+/// Nibble-reverses the address into the `....origin6.asn.cymru.com.` query name.
+fn origin_v6_name(ip: Ipv6Addr) -> String {
+    let mut name = String::with_capacity(72);
+    for byte in ip.octets().iter().rev() {
+        name.push_str(&format!("{:x}.{:x}.", byte & 0x0f, byte >> 4));
+    }
+    name.push_str("origin6.asn.cymru.com.");
+    name
+}
+
+/// Parses `"AS_number | BGP_prefix | country_code | registry | allocated_date"`.
+fn parse_origin_txt(text: &str) -> Result<(u32, IpNet, String), CymruError> {
+    let mut fields = text.split('|').map(str::trim);
+
+    let as_number = fields
+        .next()
+        .ok_or(CymruError::MalformedResponse("missing AS number field"))?
+        // prefixes announced by multiple origin ASes list them space separated; take the first
+        .split_whitespace()
+        .next()
+        .ok_or(CymruError::MalformedResponse("empty AS number field"))?
+        .parse()
+        .map_err(CymruError::AsNumberParseError)?;
+
+    let prefix: IpNet = fields
+        .next()
+        .ok_or(CymruError::MalformedResponse("missing BGP prefix field"))?
+        .parse()
+        .map_err(CymruError::NetParseError)?;
+
+    let country = fields
+        .next()
+        .ok_or(CymruError::MalformedResponse("missing country code field"))?
+        .to_owned();
+
+    Ok((as_number, prefix, country))
+}
+
+/// Parses `"AS_number | country | registry | allocated | name"`, returning the owner name.
+fn parse_as_name_txt(text: &str) -> Result<String, CymruError> {
+    text.split('|')
+        .next_back()
+        .map(|name| name.trim().to_owned())
+        .ok_or(CymruError::MalformedResponse("empty AS name response"))
+}
+
+fn range_bounds(net: &IpNet) -> (u128, u128) {
+    match net {
+        IpNet::V4(net) => (
+            u32::from(net.network()) as u128,
+            u32::from(net.broadcast()) as u128,
+        ),
+        IpNet::V6(net) => (u128::from(net.network()), u128::from(net.broadcast())),
+    }
+}
+
+/// Looks up `Record`s live over DNS using the Team Cymru IP-to-ASN service, as an alternative to
+/// loading a [`Db`](crate::Db) from a TSV/bincode file.
+pub struct CymruResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl CymruResolver {
+    /// Creates a resolver using the system's configured DNS servers.
+    pub fn new() -> Result<CymruResolver, CymruError> {
+        let resolver = TokioAsyncResolver::tokio_from_system_conf().map_err(CymruError::Resolve)?;
+        Ok(CymruResolver { resolver })
+    }
+
+    /// Looks up the ASN `Record` for an IP address. Returns `None` on NXDOMAIN (no matching
+    /// announcement), which is distinct from a DNS resolution error.
+    pub async fn lookup(&self, ip: IpAddr) -> Result<Option<Record>, CymruError> {
+        let origin_name = match ip {
+            IpAddr::V4(ip) => origin_v4_name(ip),
+            IpAddr::V6(ip) => origin_v6_name(ip),
+        };
+
+        let origin_txt = match self.txt_lookup(&origin_name).await? {
+            Some(text) => text,
+            None => return Ok(None),
+        };
+        let (as_number, prefix, country) = parse_origin_txt(&origin_txt)?;
+
+        let owner = match self
+            .txt_lookup(&format!("AS{}.asn.cymru.com.", as_number))
+            .await?
+        {
+            Some(text) => parse_as_name_txt(&text)?,
+            None => String::new(),
+        };
+
+        let (range_start, range_end) = range_bounds(&prefix);
+        Ok(Some(Record {
+            range_start,
+            range_end,
+            version: match prefix {
+                IpNet::V4(_) => IpVersion::V4,
+                IpNet::V6(_) => IpVersion::V6,
+            },
+            as_number,
+            country,
+            owner: Arc::from(owner),
+        }))
+    }
+
+    /// Issues a `TXT` query, joining multi-chunk answers and treating NXDOMAIN/no-records as `None`.
+    async fn txt_lookup(&self, name: &str) -> Result<Option<String>, CymruError> {
+        match self.resolver.txt_lookup(name).await {
+            Ok(lookup) => Ok(lookup.iter().next().map(|txt| {
+                txt.txt_data()
+                    .iter()
+                    .map(|chunk| String::from_utf8_lossy(chunk))
+                    .collect::<String>()
+            })),
+            Err(err) => match err.kind() {
+                ResolveErrorKind::NoRecordsFound { .. } => Ok(None),
+                _ => Err(CymruError::Resolve(err)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_v4_name() {
+        assert_eq!(
+            origin_v4_name("1.1.1.1".parse().unwrap()),
+            "1.1.1.1.origin.asn.cymru.com."
+        );
+    }
+
+    #[test]
+    fn test_origin_v6_name() {
+        assert_eq!(
+            origin_v6_name("2606:4700::1111".parse().unwrap()),
+            "1.1.1.1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.7.4.6.0.6.2.\
+             origin6.asn.cymru.com."
+        );
+    }
+
+    #[test]
+    fn test_parse_origin_txt() {
+        let (as_number, prefix, country) =
+            parse_origin_txt("13335 | 1.1.1.0/24 | US | arin | 2010-07-14").unwrap();
+        assert_eq!(as_number, 13335);
+        assert_eq!(prefix, "1.1.1.0/24".parse().unwrap());
+        assert_eq!(country, "US");
+    }
+
+    #[test]
+    fn test_parse_origin_txt_multiple_origin_ases() {
+        // prefixes announced by multiple origin ASes list them space separated; take the first
+        let (as_number, _, _) =
+            parse_origin_txt("13335 395152 | 1.1.1.0/24 | US | arin | 2010-07-14").unwrap();
+        assert_eq!(as_number, 13335);
+    }
+
+    #[test]
+    fn test_parse_origin_txt_malformed() {
+        assert!(matches!(
+            parse_origin_txt(""),
+            Err(CymruError::MalformedResponse(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_as_name_txt() {
+        let name =
+            parse_as_name_txt("13335 | US | arin | 2010-07-14 | CLOUDFLARENET - Cloudflare, Inc.")
+                .unwrap();
+        assert_eq!(name, "CLOUDFLARENET - Cloudflare, Inc.");
+    }
+
+    #[test]
+    fn test_range_bounds_v4() {
+        let net: IpNet = "1.1.1.0/24".parse().unwrap();
+        assert_eq!(
+            range_bounds(&net),
+            (
+                u32::from("1.1.1.0".parse::<Ipv4Addr>().unwrap()) as u128,
+                u32::from("1.1.1.255".parse::<Ipv4Addr>().unwrap()) as u128
+            )
+        );
+    }
+
+    #[test]
+    fn test_range_bounds_v6() {
+        let net: IpNet = "2606:4700::/32".parse().unwrap();
+        assert_eq!(
+            range_bounds(&net),
+            (
+                u128::from("2606:4700::".parse::<Ipv6Addr>().unwrap()),
+                u128::from(
+                    "2606:4700:ffff:ffff:ffff:ffff:ffff:ffff"
+                        .parse::<Ipv6Addr>()
+                        .unwrap()
+                )
+            )
+        );
+    }
+}
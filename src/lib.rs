@@ -1,8 +1,8 @@
 /*!
-`asn-db` is a Rust library that can load and index [ASN] database (`ip2asn-v4.tsv` file) from [IPtoASN] website.
+`asn-db` is a Rust library that can load and index [ASN] database (`ip2asn-v4.tsv` and `ip2asn-v6.tsv` files) from [IPtoASN] website.
 Once loaded it can be used to lookup an IP address for matching [ASN] record that contains:
 
-* network base IP address and mask (e.g. [ipnet::Ipv4Net](https://docs.rs/ipnet/2.3.0/ipnet/struct.Ipv4Net.html) value like `1.1.1.0/24`),
+* the announced network's inclusive IP range (e.g. `1.1.1.0` - `1.1.1.255`, re-derivable as one or more [ipnet::IpNet](https://docs.rs/ipnet/2.3.0/ipnet/enum.IpNet.html) CIDR blocks),
 * assigned AS number (e.g. `13335`),
 * owner country code (e.g. `US`),
 * owner information (e.g. `CLOUDFLARENET - Cloudflare, Inc.`).
@@ -20,84 +20,234 @@ let db = Db::form_tsv(BufReader::new(File::open("ip2asn-v4.tsv").unwrap())).unwr
 let record = db.lookup("1.1.1.1".parse().unwrap()).unwrap();
 
 println!("{:#?}", record);
-println!("{:#?}", record.network());
+println!("{:?}", record.networks().collect::<Vec<_>>());
 ```
 
 This prints:
 
 ```noformat
 Record {
-    ip: 16843008,
-    prefix_len: 24,
+    range_start: 16843008,
+    range_end: 16843263,
+    version: V4,
     as_number: 13335,
     country: "US",
     owner: "CLOUDFLARENET - Cloudflare, Inc."
 }
-1.1.1.0/24
+[1.1.1.0/24]
 ```
 
+Loading `ip2asn-v6.tsv` works the same way and records from both files can share a single `Db` -
+`db.lookup` accepts any `IpAddr` and picks the right table based on the address family.
+
 # Usage
 
-Use `Db::from_tsv(input)` to load database from `ip2asn-v4.tsv` data.
+Use `Db::form_tsv(input)` to load database from `ip2asn-v4.tsv`/`ip2asn-v6.tsv` data.
 You can then use `db.store(output)` to store the binary encoded data index for fast loading with `Db::load(input)`.
 
 Use `db.lookup(ip)` to lookup for matching record by an IP address.
 
+Enable the `dns` feature to look up records live over DNS via [`dns::CymruResolver`] instead of
+loading a `Db`, using the Team Cymru IP-to-ASN service.
+
 [ASN]: https://en.wikipedia.org/wiki/Autonomous_system_%28Internet%29#Assignment
 [IPtoASN]: https://iptoasn.com/
 */
 use bincode::{deserialize_from, serialize_into};
 use error_context::*;
-pub use ipnet::Ipv4Net;
-use ipnet::Ipv4Subnets;
+pub use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use ipnet::{Ipv4Subnets, Ipv6Subnets};
+use rangemap::RangeInclusiveMap;
 use serde::{Deserialize, Serialize};
-use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 use std::io;
 use std::io::{Read, Write};
-pub use std::net::Ipv4Addr;
+pub use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
+/// Optional live IP-to-ASN lookup over the Team Cymru DNS service; requires the `dns` feature.
+#[cfg(feature = "dns")]
+pub mod dns;
 
 const DATABASE_DATA_TAG: &[u8; 4] = b"ASDB";
-const DATABASE_DATA_VERSION: &[u8; 4] = b"bin1";
+const DATABASE_DATA_VERSION: &[u8; 4] = b"bin3";
+const DATABASE_DATA_VERSION_V2: &[u8; 4] = b"bin2";
+const DATABASE_DATA_VERSION_V1: &[u8; 4] = b"bin1";
+
+/// IP address family a `Record`'s network belongs to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum IpVersion {
+    V4,
+    V6,
+}
+
+impl IpVersion {
+    fn width(self) -> u32 {
+        match self {
+            IpVersion::V4 => 32,
+            IpVersion::V6 => 128,
+        }
+    }
+}
 
 /// Autonomous System number record.
-#[derive(Serialize, Deserialize, Debug, Clone, Eq)]
+///
+/// Deserializing `owner` requires the `rc` feature of `serde` (`Arc<T>` only implements
+/// `Deserialize` with it enabled) - make sure `Cargo.toml` lists `serde = { features = ["derive", "rc"], .. }`.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct Record {
-    /// Network base IP address (host byte order).
-    pub ip: u32,
-    /// Network mask prefix in number of bits, e.g. 24 for 255.255.255.0 mask.
-    pub prefix_len: u8,
+    /// First IP address of the announced range, inclusive (host byte order); for `V4` records only the lower 32 bits are used.
+    pub range_start: u128,
+    /// Last IP address of the announced range, inclusive (host byte order); for `V4` records only the lower 32 bits are used.
+    pub range_end: u128,
+    /// IP address family this record's range belongs to.
+    pub version: IpVersion,
     /// Assigned AS number.
     pub as_number: u32,
     /// Country code of network owner.
     pub country: String,
-    /// Network owner information.
-    pub owner: String,
+    /// Network owner information, interned per AS number so it is only stored once in a `Db`.
+    pub owner: Arc<str>,
 }
 
-impl PartialEq for Record {
-    fn eq(&self, other: &Record) -> bool {
-        self.ip == other.ip
+/// If `range_start..=range_end` is exactly one CIDR block, returns its prefix length.
+fn aligned_prefix_len(range_start: u128, range_end: u128, width: u32) -> Option<u8> {
+    if range_end < range_start {
+        return None;
     }
+    // `mask` is `size - 1`; computed this way so a full `/0` range (size == 2^128) doesn't
+    // overflow `range_end - range_start + 1`.
+    let mask = range_end - range_start;
+    let host_bits = match mask.checked_add(1) {
+        Some(size) if size.is_power_of_two() => size.trailing_zeros(),
+        Some(_) => return None,
+        None => 128,
+    };
+    if host_bits > width || range_start & mask != 0 {
+        return None;
+    }
+    Some((width - host_bits) as u8)
 }
 
-impl Ord for Record {
-    fn cmp(&self, other: &Record) -> Ordering {
-        self.ip.cmp(&other.ip)
+impl Record {
+    /// Gets the first IP address of the announced range, inclusive.
+    ///
+    /// Named `first_addr` (not `range_start`) so it doesn't collide with the `range_start` field.
+    pub fn first_addr(&self) -> IpAddr {
+        match self.version {
+            IpVersion::V4 => IpAddr::V4(Ipv4Addr::from(self.range_start as u32)),
+            IpVersion::V6 => IpAddr::V6(Ipv6Addr::from(self.range_start)),
+        }
+    }
+
+    /// Gets the last IP address of the announced range, inclusive.
+    ///
+    /// Named `last_addr` (not `range_end`) so it doesn't collide with the `range_end` field.
+    pub fn last_addr(&self) -> IpAddr {
+        match self.version {
+            IpVersion::V4 => IpAddr::V4(Ipv4Addr::from(self.range_end as u32)),
+            IpVersion::V6 => IpAddr::V6(Ipv6Addr::from(self.range_end)),
+        }
+    }
+
+    /// Gets `IpNet` representation of the network address, if the announced range happens to be
+    /// exactly one CIDR block. Use `networks()` to cover ranges that are not CIDR-aligned.
+    pub fn network(&self) -> Option<IpNet> {
+        let prefix_len =
+            aligned_prefix_len(self.range_start, self.range_end, self.version.width())?;
+        Some(match self.version {
+            IpVersion::V4 => IpNet::V4(
+                Ipv4Net::new(Ipv4Addr::from(self.range_start as u32), prefix_len)
+                    .expect("bad network"),
+            ),
+            IpVersion::V6 => IpNet::V6(
+                Ipv6Net::new(Ipv6Addr::from(self.range_start), prefix_len).expect("bad network"),
+            ),
+        })
+    }
+
+    /// Re-derives every CIDR block that together cover the announced range.
+    pub fn networks(&self) -> Box<dyn Iterator<Item = IpNet>> {
+        match self.version {
+            IpVersion::V4 => Box::new(
+                Ipv4Subnets::new(
+                    Ipv4Addr::from(self.range_start as u32),
+                    Ipv4Addr::from(self.range_end as u32),
+                    0,
+                )
+                .map(IpNet::V4),
+            ),
+            IpVersion::V6 => Box::new(
+                Ipv6Subnets::new(
+                    Ipv6Addr::from(self.range_start),
+                    Ipv6Addr::from(self.range_end),
+                    0,
+                )
+                .map(IpNet::V6),
+            ),
+        }
     }
+
+    /// Whether this record's range is actually announced by an AS.
+    ///
+    /// Always `true` unless the `Db` was built with `TsvOptions { keep_unrouted: true, .. }`, in
+    /// which case reserved/unannounced blocks are kept with `as_number == 0` instead of being
+    /// dropped, and this returns `false` for them.
+    pub fn is_routed(&self) -> bool {
+        self.as_number != 0
+    }
+}
+
+/// Layout of a `Record` as stored in `bin1` database files (IPv4 only, CIDR block, no `version` tag).
+#[derive(Deserialize)]
+struct RecordV1 {
+    ip: u32,
+    prefix_len: u8,
+    as_number: u32,
+    country: String,
+    owner: String,
 }
 
-impl PartialOrd for Record {
-    fn partial_cmp(&self, other: &Record) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl RecordV1 {
+    fn into_record(self) -> Record {
+        let host_bits = 32 - self.prefix_len as u32;
+        let size: u128 = (1u128 << host_bits) - 1;
+        Record {
+            range_start: self.ip as u128,
+            range_end: self.ip as u128 | size,
+            version: IpVersion::V4,
+            as_number: self.as_number,
+            country: self.country,
+            owner: Arc::from(self.owner),
+        }
     }
 }
 
-impl Record {
-    /// Gets `Ipv4Net` representation of the network address.
-    pub fn network(&self) -> Ipv4Net {
-        Ipv4Net::new(self.ip.into(), self.prefix_len).expect("bad network")
+/// Layout of a `Record` as stored in `bin2` database files (CIDR block, no explicit range).
+#[derive(Deserialize)]
+struct RecordV2 {
+    ip: u128,
+    prefix_len: u8,
+    version: IpVersion,
+    as_number: u32,
+    country: String,
+    owner: Arc<str>,
+}
+
+impl RecordV2 {
+    fn into_record(self) -> Record {
+        let host_bits = self.version.width() - self.prefix_len as u32;
+        let size: u128 = 1u128.checked_shl(host_bits).map_or(u128::MAX, |v| v - 1);
+        Record {
+            range_start: self.ip,
+            range_end: self.ip | size,
+            version: self.version,
+            as_number: self.as_number,
+            country: self.country,
+            owner: self.owner,
+        }
     }
 }
 
@@ -106,6 +256,7 @@ pub enum TsvParseError {
     TsvError(csv::Error),
     AddrFieldParseError(std::net::AddrParseError, &'static str),
     IntFieldParseError(std::num::ParseIntError, &'static str),
+    MixedAddressFamily,
 }
 
 impl fmt::Display for TsvParseError {
@@ -118,6 +269,9 @@ impl fmt::Display for TsvParseError {
             TsvParseError::IntFieldParseError(_, context) => {
                 write!(f, "error parsing integer while {}", context)
             }
+            TsvParseError::MixedAddressFamily => {
+                write!(f, "range_start and range_end IP address families differ")
+            }
         }
     }
 }
@@ -128,6 +282,7 @@ impl Error for TsvParseError {
             TsvParseError::TsvError(err) => Some(err),
             TsvParseError::AddrFieldParseError(err, _) => Some(err),
             TsvParseError::IntFieldParseError(err, _) => Some(err),
+            TsvParseError::MixedAddressFamily => None,
         }
     }
 }
@@ -150,12 +305,36 @@ impl From<ErrorContext<std::num::ParseIntError, &'static str>> for TsvParseError
     }
 }
 
-/// Reads ASN database TSV file (`ip2asn-v4.tsv` format) provided by [IPtoASN](https://iptoasn.com/) as iterator of `Record`s.
+/// Options controlling how `read_asn_tsv_with_options`/`Db::from_tsv_with_options` parse a TSV file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TsvOptions {
+    /// Keep rows whose owner is `"Not routed"` or `"None"` instead of dropping them (the default
+    /// behavior). The source data already marks these rows `as_number == 0`, so `Record::is_routed()`
+    /// returns `false` for them. A country field of `"None"` is normalized to `"??"` on every row,
+    /// whether or not this option is set; other country values are kept as they are.
+    pub keep_unrouted: bool,
+}
+
+/// Reads ASN database TSV file (`ip2asn-v4.tsv`/`ip2asn-v6.tsv` format) provided by [IPtoASN](https://iptoasn.com/) as iterator of `Record`s.
+///
+/// Each row becomes exactly one `Record` holding the original inclusive range - no subnet splitting is performed.
+/// Rows for unrouted/reserved blocks are dropped; use `read_asn_tsv_with_options` to keep them.
 pub fn read_asn_tsv<'d, R: io::Read>(
     data: &'d mut csv::Reader<R>,
+) -> impl Iterator<Item = Result<Record, TsvParseError>> + 'd {
+    read_asn_tsv_with_options(data, TsvOptions::default())
+}
+
+/// Like `read_asn_tsv`, but with control over whether unrouted/reserved blocks are retained.
+pub fn read_asn_tsv_with_options<'d, R: io::Read>(
+    data: &'d mut csv::Reader<R>,
+    options: TsvOptions,
 ) -> impl Iterator<Item = Result<Record, TsvParseError>> + 'd {
     data.records()
-        .filter(|record| {
+        .filter(move |record| {
+            if options.keep_unrouted {
+                return true;
+            }
             if let Ok(record) = record {
                 let owner = &record[4];
                 !(owner == "Not routed" || owner == "None")
@@ -166,45 +345,46 @@ pub fn read_asn_tsv<'d, R: io::Read>(
         .map(|record| record.map_err(Into::<TsvParseError>::into))
         .map(|record| {
             record.and_then(|record| {
-                let range_start: Ipv4Addr = record[0]
+                let range_start: IpAddr = record[0]
                     .parse()
                     .wrap_error_while("parsing range_start IP")?;
-                let range_end: Ipv4Addr =
+                let range_end: IpAddr =
                     record[1].parse().wrap_error_while("parsing range_end IP")?;
                 let as_number: u32 = record[2].parse().wrap_error_while("parsing as_number")?;
                 let country = record[3].to_owned();
+                let country = if country == "None" {
+                    "??".to_owned()
+                } else {
+                    country
+                };
                 let owner = record[4].to_owned();
                 Ok((range_start, range_end, as_number, country, owner))
             })
         })
         .map(|record| {
-            record.map(|(range_start, range_end, as_number, country, owner)| {
-                // Convert range into one or more subnets iterator
-                Ipv4Subnets::new(range_start, range_end, 8).map(move |subnet| Record {
-                    ip: subnet.network().into(),
-                    prefix_len: subnet.prefix_len(),
-                    country: country.clone(),
-                    as_number,
-                    owner: owner.clone(),
-                })
+            record.and_then(|(range_start, range_end, as_number, country, owner)| {
+                let owner: Arc<str> = Arc::from(owner);
+                match (range_start, range_end) {
+                    (IpAddr::V4(range_start), IpAddr::V4(range_end)) => Ok(Record {
+                        range_start: u32::from(range_start) as u128,
+                        range_end: u32::from(range_end) as u128,
+                        version: IpVersion::V4,
+                        as_number,
+                        country,
+                        owner,
+                    }),
+                    (IpAddr::V6(range_start), IpAddr::V6(range_end)) => Ok(Record {
+                        range_start: u128::from(range_start),
+                        range_end: u128::from(range_end),
+                        version: IpVersion::V6,
+                        as_number,
+                        country,
+                        owner,
+                    }),
+                    _ => Err(TsvParseError::MixedAddressFamily),
+                }
             })
         })
-        .flat_map(|subnet_records| {
-            // Flatten many records or single error
-            let mut records = None;
-            let mut error = None;
-
-            match subnet_records {
-                Ok(subnet_records) => records = Some(subnet_records),
-                Err(err) => error = Some(TsvParseError::from(err)),
-            }
-
-            records
-                .into_iter()
-                .flatten()
-                .map(Ok)
-                .chain(error.into_iter().map(Err))
-        })
 }
 
 #[derive(Debug)]
@@ -261,32 +441,114 @@ impl From<ErrorContext<bincode::Error, &'static str>> for DbError {
     }
 }
 
-//TODO: Use eytzinger layout - requires non exact search support.
 //TODO: Support for mmap'ed files to reduce memory usage?
-//TODO: IPv6 support.
-//TODO: Support providing all subnets of matched range.
 /// ASN record database that is optimized for lookup by an IP address.
-pub struct Db(Vec<Record>);
+///
+/// IPv4 and IPv6 records are kept in separate `RangeInclusiveMap`s keyed by the record's
+/// original announced range, so lookup is a single predecessor search with no subnet
+/// reconstruction. A reverse index from AS number to its canonical owner name and announced
+/// networks is built alongside the tables.
+pub struct Db {
+    v4: RangeInclusiveMap<u128, Arc<Record>>,
+    v6: RangeInclusiveMap<u128, Arc<Record>>,
+    asn_names: HashMap<u32, Arc<str>>,
+    asn_networks: HashMap<u32, Vec<Arc<Record>>>,
+}
 
 impl fmt::Debug for Db {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "asn_db::Db[total records: {}]", self.0.len())
+        write!(
+            f,
+            "asn_db::Db[v4 ranges: {}, v6 ranges: {}, AS numbers: {}]",
+            self.v4.iter().count(),
+            self.v6.iter().count(),
+            self.asn_names.len()
+        )
+    }
+}
+
+/// Interns `records[*].owner` per AS number and inserts each record into `map` keyed by its range.
+///
+/// Each record is wrapped in a single `Arc` shared between `map` and `asn_networks`, so a `Db`
+/// holds one copy of every record rather than one per index.
+fn index_records(
+    records: Vec<Record>,
+    asn_names: &mut HashMap<u32, Arc<str>>,
+    asn_networks: &mut HashMap<u32, Vec<Arc<Record>>>,
+    map: &mut RangeInclusiveMap<u128, Arc<Record>>,
+) {
+    for mut record in records {
+        let name = asn_names
+            .entry(record.as_number)
+            .or_insert_with(|| record.owner.clone())
+            .clone();
+        record.owner = name;
+
+        let range = record.range_start..=record.range_end;
+        let record = Arc::new(record);
+
+        asn_networks
+            .entry(record.as_number)
+            .or_default()
+            .push(record.clone());
+
+        map.insert(range, record);
     }
 }
 
 impl Db {
-    /// Loads database from ASN data as provided by [IPtoASN](https://iptoasn.com/) - the only supported file format is of `ip2asn-v4.tsv` file.
+    fn from_tables(v4: Vec<Record>, v6: Vec<Record>) -> Db {
+        let mut asn_names = HashMap::new();
+        let mut asn_networks = HashMap::new();
+        let mut v4_map = RangeInclusiveMap::new();
+        let mut v6_map = RangeInclusiveMap::new();
+
+        index_records(v4, &mut asn_names, &mut asn_networks, &mut v4_map);
+        index_records(v6, &mut asn_names, &mut asn_networks, &mut v6_map);
+
+        Db {
+            v4: v4_map,
+            v6: v6_map,
+            asn_names,
+            asn_networks,
+        }
+    }
+
+    /// Loads database from ASN data as provided by [IPtoASN](https://iptoasn.com/) - both `ip2asn-v4.tsv` and `ip2asn-v6.tsv` file formats are supported.
+    ///
+    /// Rows for unrouted/reserved blocks are dropped; use `from_tsv_with_options` to keep them.
+    ///
+    /// Despite the name, this is not a typo'd sibling of `from_tsv_with_options` with fewer
+    /// features - it is kept under its original (misspelled) name for backwards compatibility and
+    /// simply calls `from_tsv_with_options` with the default options.
     pub fn form_tsv(data: impl Read) -> Result<Db, DbError> {
+        Db::from_tsv_with_options(data, TsvOptions::default())
+    }
+
+    /// Like `form_tsv`, but with control over whether unrouted/reserved blocks are retained.
+    pub fn from_tsv_with_options(data: impl Read, options: TsvOptions) -> Result<Db, DbError> {
         let mut rdr = csv::ReaderBuilder::new()
             .delimiter(b'\t')
             .has_headers(false)
             .from_reader(data);
-        let mut records = read_asn_tsv(&mut rdr).collect::<Result<Vec<_>, _>>()?;
-        records.sort();
-        Ok(Db(records))
+
+        let mut v4 = Vec::new();
+        let mut v6 = Vec::new();
+        for record in read_asn_tsv_with_options(&mut rdr, options) {
+            let record = record?;
+            match record.version {
+                IpVersion::V4 => v4.push(record),
+                IpVersion::V6 => v6.push(record),
+            }
+        }
+
+        Ok(Db::from_tables(v4, v6))
     }
 
     /// Loads database from the binary index that was stored with `.store()` - this method is much faster than loading from the TSV file.
+    ///
+    /// The current `bin3` format (inclusive ranges) as well as the legacy `bin2` (CIDR blocks, IPv4+IPv6)
+    /// and `bin1` (CIDR blocks, IPv4 only) formats are accepted.
     pub fn load(mut db_data: impl Read) -> Result<Db, DbError> {
         let mut tag = [0; 4];
         db_data
@@ -300,14 +562,28 @@ impl Db {
         db_data
             .read_exact(&mut version)
             .wrap_error_while("reading database version")?;
-        if &version != DATABASE_DATA_VERSION {
-            return Err(DbError::DbDataError("unsuported database version"));
-        }
 
-        let records: Vec<Record> =
-            deserialize_from(db_data).wrap_error_while("reading bincode DB file")?;
-
-        Ok(Db(records))
+        match &version {
+            DATABASE_DATA_VERSION => {
+                let (v4, v6): (Vec<Record>, Vec<Record>) =
+                    deserialize_from(db_data).wrap_error_while("reading bincode DB file")?;
+                Ok(Db::from_tables(v4, v6))
+            }
+            DATABASE_DATA_VERSION_V2 => {
+                let (v4, v6): (Vec<RecordV2>, Vec<RecordV2>) =
+                    deserialize_from(db_data).wrap_error_while("reading bincode DB file")?;
+                let v4 = v4.into_iter().map(RecordV2::into_record).collect();
+                let v6 = v6.into_iter().map(RecordV2::into_record).collect();
+                Ok(Db::from_tables(v4, v6))
+            }
+            DATABASE_DATA_VERSION_V1 => {
+                let records: Vec<RecordV1> =
+                    deserialize_from(db_data).wrap_error_while("reading bincode DB file")?;
+                let v4 = records.into_iter().map(RecordV1::into_record).collect();
+                Ok(Db::from_tables(v4, Vec::new()))
+            }
+            _ => Err(DbError::DbDataError("unsuported database version")),
+        }
     }
 
     /// Stores database as a binary index for fast loading with `.load()`.
@@ -318,25 +594,59 @@ impl Db {
         db_data
             .write(DATABASE_DATA_VERSION)
             .wrap_error_while("error writing version")?;
-        serialize_into(db_data, &self.0).wrap_error_while("stroing DB")?;
+
+        let v4: Vec<&Record> = self.v4.iter().map(|(_, record)| record.as_ref()).collect();
+        let v6: Vec<&Record> = self.v6.iter().map(|(_, record)| record.as_ref()).collect();
+        serialize_into(db_data, &(v4, v6)).wrap_error_while("stroing DB")?;
         Ok(())
     }
 
-    /// Performs lookup by an IP address for the ASN `Record` of which network this IP belongs to.
-    pub fn lookup(&self, ip: Ipv4Addr) -> Option<&Record> {
-        match self.0.binary_search_by_key(&ip.into(), |record| record.ip) {
-            Ok(index) => return Some(&self.0[index]), // IP was network base IP
-            Err(index) => {
-                // upper bound/insert index
-                if index != 0 {
-                    let record = &self.0[index - 1];
-                    if record.network().contains(&ip) {
-                        return Some(record);
-                    }
-                }
-            }
+    /// Performs lookup by an IP address for the ASN `Record` of which announced range this IP belongs to.
+    pub fn lookup(&self, ip: IpAddr) -> Option<&Record> {
+        match ip {
+            IpAddr::V4(ip) => self.v4.get(&(u32::from(ip) as u128)),
+            IpAddr::V6(ip) => self.v6.get(&u128::from(ip)),
+        }
+        .map(Arc::as_ref)
+    }
+
+    /// Performs lookup by an IP address, returning the full matched inclusive range rather than the `Record`.
+    pub fn lookup_range(&self, ip: IpAddr) -> Option<std::ops::RangeInclusive<IpAddr>> {
+        let record = self.lookup(ip)?;
+        Some(record.first_addr()..=record.last_addr())
+    }
+
+    /// Looks up the canonical owner name registered for an AS number.
+    pub fn lookup_asn(&self, as_number: u32) -> Option<&str> {
+        self.asn_names.get(&as_number).map(AsRef::as_ref)
+    }
+
+    /// Iterates over all networks announced by an AS number, in no particular order.
+    pub fn networks_for_asn(&self, as_number: u32) -> impl Iterator<Item = &Record> {
+        self.asn_networks
+            .get(&as_number)
+            .into_iter()
+            .flatten()
+            .map(Arc::as_ref)
+    }
+
+    /// Writes a deduplicated `asn\tname\tcountry` table covering every AS number known to this `Db`.
+    pub fn export_asn_map(&self, mut writer: impl Write) -> Result<(), DbError> {
+        let mut as_numbers: Vec<&u32> = self.asn_names.keys().collect();
+        as_numbers.sort();
+
+        for &as_number in as_numbers {
+            let name = &self.asn_names[&as_number];
+            let country = self
+                .networks_for_asn(as_number)
+                .next()
+                .map(|record| record.country.as_str())
+                .unwrap_or("??");
+
+            writeln!(writer, "{}\t{}\t{}", as_number, name, country)
+                .wrap_error_while("writing ASN map entry")?;
         }
-        None
+        Ok(())
     }
 }
 
@@ -414,4 +724,174 @@ mod tests {
             .owner
             .contains("GOOGLE"));
     }
+
+    #[test]
+    fn test_tsv_mixed_v4_and_v6() {
+        let tsv = b"1.1.1.0\t1.1.1.255\t13335\tUS\tCLOUDFLARENET - Cloudflare, Inc.\n\
+                    2606:4700::\t2606:4700:ffff:ffff:ffff:ffff:ffff:ffff\t13335\tUS\tCLOUDFLARENET - Cloudflare, Inc.\n";
+        let db = Db::form_tsv(&tsv[..]).unwrap();
+
+        let v4 = db.lookup("1.1.1.1".parse().unwrap()).unwrap();
+        assert_eq!(v4.as_number, 13335);
+        assert_eq!(v4.version, IpVersion::V4);
+
+        let v6 = db.lookup("2606:4700::1".parse().unwrap()).unwrap();
+        assert_eq!(v6.as_number, 13335);
+        assert_eq!(v6.version, IpVersion::V6);
+
+        assert!(db.lookup("8.8.8.8".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_record_v1_into_record() {
+        let v1 = RecordV1 {
+            ip: u32::from(Ipv4Addr::new(1, 1, 1, 0)),
+            prefix_len: 24,
+            as_number: 13335,
+            country: "US".to_owned(),
+            owner: "CLOUDFLARENET".to_owned(),
+        };
+        let record = v1.into_record();
+
+        assert_eq!(record.version, IpVersion::V4);
+        assert_eq!(
+            record.range_start,
+            u32::from(Ipv4Addr::new(1, 1, 1, 0)) as u128
+        );
+        assert_eq!(
+            record.range_end,
+            u32::from(Ipv4Addr::new(1, 1, 1, 255)) as u128
+        );
+        assert_eq!(record.network(), Some("1.1.1.0/24".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_asn_reverse_index() {
+        let tsv = b"1.1.1.0\t1.1.1.255\t13335\tUS\tCLOUDFLARENET - Cloudflare, Inc.\n\
+                    1.0.0.0\t1.0.0.255\t13335\tUS\tCLOUDFLARENET - Cloudflare, Inc.\n\
+                    8.8.8.0\t8.8.8.255\t15169\tUS\tGOOGLE\n";
+        let db = Db::form_tsv(&tsv[..]).unwrap();
+
+        assert_eq!(
+            db.lookup_asn(13335),
+            Some("CLOUDFLARENET - Cloudflare, Inc.")
+        );
+        assert_eq!(db.lookup_asn(15169), Some("GOOGLE"));
+        assert_eq!(db.lookup_asn(64512), None);
+
+        let cloudflare_networks: Vec<_> = db.networks_for_asn(13335).collect();
+        assert_eq!(cloudflare_networks.len(), 2);
+
+        // owner strings for the same AS number are interned, not duplicated
+        assert!(Arc::ptr_eq(
+            &cloudflare_networks[0].owner,
+            &cloudflare_networks[1].owner
+        ));
+
+        let mut map = Vec::new();
+        db.export_asn_map(&mut map).unwrap();
+        let map = String::from_utf8(map).unwrap();
+        assert!(map.contains("13335\tCLOUDFLARENET - Cloudflare, Inc.\tUS"));
+        assert!(map.contains("15169\tGOOGLE\tUS"));
+    }
+
+    #[test]
+    fn test_network_cidr_aligned() {
+        let tsv = b"1.1.1.0\t1.1.1.255\t13335\tUS\tCLOUDFLARENET\n";
+        let db = Db::form_tsv(&tsv[..]).unwrap();
+        let record = db.lookup("1.1.1.128".parse().unwrap()).unwrap();
+
+        assert_eq!(record.network(), Some("1.1.1.0/24".parse().unwrap()));
+        assert_eq!(
+            record.networks().collect::<Vec<_>>(),
+            vec!["1.1.1.0/24".parse().unwrap()]
+        );
+        assert_eq!(record.first_addr(), "1.1.1.0".parse::<IpAddr>().unwrap());
+        assert_eq!(record.last_addr(), "1.1.1.255".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_network_not_cidr_aligned() {
+        // 1.1.1.1 - 1.1.1.2 is not a single CIDR block
+        let tsv = b"1.1.1.1\t1.1.1.2\t13335\tUS\tCLOUDFLARENET\n";
+        let db = Db::form_tsv(&tsv[..]).unwrap();
+        let record = db.lookup("1.1.1.1".parse().unwrap()).unwrap();
+
+        assert_eq!(record.network(), None);
+        assert_eq!(
+            record.networks().collect::<Vec<_>>(),
+            vec!["1.1.1.1/32".parse().unwrap(), "1.1.1.2/32".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_network_full_v6_range_does_not_overflow() {
+        // range_start=0, range_end=u128::MAX is exactly what a legacy bin2 `ip: 0, prefix_len: 0,
+        // version: V6` record decodes to - `network()` must not panic computing its size.
+        let record = RecordV2 {
+            ip: 0,
+            prefix_len: 0,
+            version: IpVersion::V6,
+            as_number: 1,
+            country: "US".to_owned(),
+            owner: Arc::from("TEST"),
+        }
+        .into_record();
+
+        assert_eq!(record.range_start, 0);
+        assert_eq!(record.range_end, u128::MAX);
+        assert_eq!(record.network(), Some("::/0".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_lookup_range() {
+        let tsv = b"1.1.1.1\t1.1.1.2\t13335\tUS\tCLOUDFLARENET\n";
+        let db = Db::form_tsv(&tsv[..]).unwrap();
+
+        let range = db.lookup_range("1.1.1.1".parse().unwrap()).unwrap();
+        assert_eq!(*range.start(), "1.1.1.1".parse::<IpAddr>().unwrap());
+        assert_eq!(*range.end(), "1.1.1.2".parse::<IpAddr>().unwrap());
+
+        assert!(db.lookup_range("8.8.8.8".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_keep_unrouted() {
+        let tsv = b"1.1.1.0\t1.1.1.255\t13335\tUS\tCLOUDFLARENET\n\
+                    5.5.5.0\t5.5.5.255\t0\tNone\tNone\n";
+
+        let db = Db::form_tsv(&tsv[..]).unwrap();
+        assert!(db.lookup("5.5.5.5".parse().unwrap()).is_none());
+
+        let db = Db::from_tsv_with_options(
+            &tsv[..],
+            TsvOptions {
+                keep_unrouted: true,
+            },
+        )
+        .unwrap();
+        let record = db.lookup("5.5.5.5".parse().unwrap()).unwrap();
+        assert_eq!(record.as_number, 0);
+        assert_eq!(record.country, "??");
+        assert!(!record.is_routed());
+    }
+
+    #[test]
+    fn test_is_routed_trusts_as_number_even_for_none_owner() {
+        // a "None"-owned row kept via `keep_unrouted` that happens to carry a non-zero AS number
+        // is reported as routed - `is_routed()` trusts the TSV's `as_number` field rather than
+        // forcing it to zero based on the owner text.
+        let tsv = b"6.6.6.0\t6.6.6.255\t64512\tUS\tNone\n";
+        let db = Db::from_tsv_with_options(
+            &tsv[..],
+            TsvOptions {
+                keep_unrouted: true,
+            },
+        )
+        .unwrap();
+
+        let record = db.lookup("6.6.6.6".parse().unwrap()).unwrap();
+        assert_eq!(record.as_number, 64512);
+        assert!(record.is_routed());
+    }
 }